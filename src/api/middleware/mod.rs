@@ -0,0 +1,3 @@
+mod rate_limit;
+
+pub use rate_limit::{RateLimitMiddleware, RateLimitRoute};
@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use poem::http::{HeaderValue, StatusCode};
+use poem::{Endpoint, IntoResponse, Middleware, Request, Response, Result};
+use tokio::sync::Mutex;
+
+use crate::database::entities::Config;
+
+/// How often the bucket janitor sweeps for windows that have already elapsed. Buckets aren't
+/// needed again until their caller comes back, so there's no harm in letting one sit briefly
+/// past its `reset_at` before it's reclaimed.
+const BUCKET_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// One fixed-window token bucket, identified by whatever key the owning middleware derives for a
+/// request (currently: route name + caller identity).
+struct Bucket {
+    remaining: u32,
+    reset_at: Instant,
+}
+
+/// Which bucket of `Config.limits.rate.routes` a [`RateLimitMiddleware`] reads its limit/window
+/// from, matching the groupings the rest of the route tree is already organized by (the same
+/// `limits` tree `add_pinned_message` reads `max_pins` from).
+#[derive(Debug, Clone, Copy)]
+pub enum RateLimitRoute {
+    Users,
+    Channel,
+    Guild,
+}
+
+impl RateLimitRoute {
+    fn limit_and_window(self, config: &Config) -> (u32, Duration) {
+        let options = match self {
+            Self::Users => &config.limits.rate.routes.users,
+            Self::Channel => &config.limits.rate.routes.channel,
+            Self::Guild => &config.limits.rate.routes.guild,
+        };
+        (options.count as u32, Duration::from_secs(options.window))
+    }
+}
+
+/// A `poem` middleware enforcing a simple fixed-window rate limit, configured per-route from the
+/// values under [`Config`]'s `limits` tree rather than a hardcoded limit/window.
+///
+/// Buckets are keyed by the caller's `Authorization` header when present (so each user gets their
+/// own budget) and fall back to the remote address for unauthenticated requests, mirroring how
+/// chorus scopes its `LimitType::Route`/`LimitType::Auth` buckets.
+pub struct RateLimitMiddleware {
+    route: &'static str,
+    route_limits: RateLimitRoute,
+    buckets: Arc<Mutex<HashMap<String, Bucket>>>,
+}
+
+impl RateLimitMiddleware {
+    pub fn new(route: &'static str, route_limits: RateLimitRoute) -> Self {
+        let middleware = Self {
+            route,
+            route_limits,
+            buckets: Arc::new(Mutex::new(HashMap::new())),
+        };
+        middleware.spawn_janitor();
+        middleware
+    }
+
+    /// Spawns a background task that periodically drops elapsed buckets, so the map doesn't grow
+    /// forever as new callers show up over the life of the process.
+    fn spawn_janitor(&self) {
+        let buckets = self.buckets.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(BUCKET_SWEEP_INTERVAL);
+            loop {
+                interval.tick().await;
+                let now = Instant::now();
+                buckets.lock().await.retain(|_, bucket| bucket.reset_at > now);
+            }
+        });
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for RateLimitMiddleware {
+    type Output = RateLimitEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        RateLimitEndpoint {
+            inner: ep,
+            route: self.route,
+            route_limits: self.route_limits,
+            buckets: self.buckets.clone(),
+        }
+    }
+}
+
+pub struct RateLimitEndpoint<E> {
+    inner: E,
+    route: &'static str,
+    route_limits: RateLimitRoute,
+    buckets: Arc<Mutex<HashMap<String, Bucket>>>,
+}
+
+/// The outcome of consulting a bucket: either the request may proceed with `remaining` slots left
+/// in the current window, or it must be rejected until `reset_at`.
+enum Decision {
+    Allowed {
+        limit: u32,
+        remaining: u32,
+        reset_at: Instant,
+    },
+    Limited {
+        limit: u32,
+        reset_at: Instant,
+    },
+}
+
+fn bucket_key(route: &str, req: &Request) -> String {
+    let identity = req
+        .header("Authorization")
+        .map(str::to_owned)
+        .or_else(|| {
+            req.remote_addr()
+                .as_socket_addr()
+                .map(|addr| addr.to_string())
+        })
+        .unwrap_or_else(|| "unknown".to_string());
+    format!("{route}:{identity}")
+}
+
+impl<E> RateLimitEndpoint<E> {
+    /// Reads the limit/window from `Config` on every call (rather than caching it once at
+    /// startup) so a reloaded config is picked up for new buckets without restarting the
+    /// process. Callers with a bucket already open keep that bucket's limit/window until it
+    /// next resets, same as chorus' own rate limiter does when its config changes mid-window.
+    fn limit_and_window(&self, req: &Request) -> (u32, Duration) {
+        let config = req
+            .data::<Config>()
+            .expect("RateLimitMiddleware requires Config to be available as request data");
+        self.route_limits.limit_and_window(config)
+    }
+
+    async fn decide(&self, req: &Request) -> Decision {
+        let (limit, window) = self.limit_and_window(req);
+        let key = bucket_key(self.route, req);
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().await;
+        let bucket = buckets.entry(key).or_insert_with(|| Bucket {
+            remaining: limit,
+            reset_at: now + window,
+        });
+
+        if now >= bucket.reset_at {
+            bucket.remaining = limit;
+            bucket.reset_at = now + window;
+        }
+
+        if bucket.remaining == 0 {
+            Decision::Limited {
+                limit,
+                reset_at: bucket.reset_at,
+            }
+        } else {
+            bucket.remaining -= 1;
+            Decision::Allowed {
+                limit,
+                remaining: bucket.remaining,
+                reset_at: bucket.reset_at,
+            }
+        }
+    }
+}
+
+#[poem::async_trait]
+impl<E: Endpoint> Endpoint for RateLimitEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        match self.decide(&req).await {
+            Decision::Limited { limit, reset_at } => {
+                let mut response = Response::builder()
+                    .status(StatusCode::TOO_MANY_REQUESTS)
+                    .finish();
+                let retry_after = reset_at.saturating_duration_since(Instant::now());
+                insert_rate_limit_headers(&mut response, limit, 0, reset_at);
+                response.headers_mut().insert(
+                    "Retry-After",
+                    HeaderValue::from_str(&retry_after.as_secs().to_string()).unwrap(),
+                );
+                Ok(response)
+            }
+            Decision::Allowed {
+                limit,
+                remaining,
+                reset_at,
+            } => {
+                let mut response = self.inner.call(req).await?.into_response();
+                insert_rate_limit_headers(&mut response, limit, remaining, reset_at);
+                Ok(response)
+            }
+        }
+    }
+}
+
+fn insert_rate_limit_headers(response: &mut Response, limit: u32, remaining: u32, reset_at: Instant) {
+    let reset_epoch = (SystemTime::now() + reset_at.saturating_duration_since(Instant::now()))
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    let headers = response.headers_mut();
+    headers.insert(
+        "X-RateLimit-Limit",
+        HeaderValue::from_str(&limit.to_string()).unwrap(),
+    );
+    headers.insert(
+        "X-RateLimit-Remaining",
+        HeaderValue::from_str(&remaining.to_string()).unwrap(),
+    );
+    headers.insert(
+        "X-RateLimit-Reset",
+        HeaderValue::from_str(&reset_epoch.to_string()).unwrap(),
+    );
+}
@@ -1,22 +1,38 @@
-use chorus::types::{jwt::Claims, Snowflake};
+use chorus::types::{jwt::Claims, ChannelPinsUpdateEvent, MessageUpdateEvent, Snowflake};
 use poem::{
-    handler,
+    get, handler,
     http::StatusCode,
+    put,
     web::{Data, Json, Path},
-    IntoResponse, Response,
+    EndpointExt, IntoResponse, Response, Route,
 };
-use sqlx::MySqlPool;
 
 use crate::{
-    database::entities::{Config, Message},
+    api::middleware::{RateLimitMiddleware, RateLimitRoute},
+    database::{
+        backend::Database,
+        entities::{AuditLogEntry, Config, Message},
+    },
     errors::{ChannelError, Error},
+    gateway::dispatch::{DispatchEvent, DispatchRegistry, Topic},
 };
 
+pub fn setup_routes() -> Route {
+    Route::new()
+        .at(
+            "/:message_id",
+            put(add_pinned_message).delete(remove_pinned_message),
+        )
+        .at("/", get(get_pinned_messages))
+        .with(RateLimitMiddleware::new("channel_pins", RateLimitRoute::Channel))
+}
+
 #[handler]
 pub async fn add_pinned_message(
-    Data(db): Data<&MySqlPool>,
+    Data(db): Data<&Database>,
     Data(config): Data<&Config>,
     Data(claims): Data<&Claims>,
+    Data(dispatch): Data<&DispatchRegistry>,
     Path((channel_id, message_id)): Path<(Snowflake, Snowflake)>,
 ) -> poem::Result<impl IntoResponse> {
     let mut message = Message::get_by_id(db, channel_id, message_id)
@@ -33,15 +49,17 @@ pub async fn add_pinned_message(
     }
 
     message.set_pinned(db, true).await?;
-    // TODO: Emit events 'MESSAGE_UPDATE' AND 'CHANNEL_PINS_UPDATE'
+    record_pin_audit_log(db, claims.id, channel_id, message_id, &message, true).await;
+    emit_pin_events(dispatch, channel_id, &message).await;
 
     Ok(Response::builder().status(StatusCode::NO_CONTENT).finish())
 }
 
 #[handler]
 pub async fn remove_pinned_message(
-    Data(db): Data<&MySqlPool>,
+    Data(db): Data<&Database>,
     Data(claims): Data<&Claims>,
+    Data(dispatch): Data<&DispatchRegistry>,
     Path((channel_id, message_id)): Path<(Snowflake, Snowflake)>,
 ) -> poem::Result<impl IntoResponse> {
     let mut message = Message::get_by_id(db, channel_id, message_id)
@@ -53,14 +71,61 @@ pub async fn remove_pinned_message(
     }
 
     message.set_pinned(db, false).await?;
-    // TODO: Emit events 'MESSAGE_UPDATE' AND 'CHANNEL_PINS_UPDATE'
+    record_pin_audit_log(db, claims.id, channel_id, message_id, &message, false).await;
+    emit_pin_events(dispatch, channel_id, &message).await;
 
     Ok(Response::builder().status(StatusCode::NO_CONTENT).finish())
 }
 
+/// Records a `MESSAGE_PIN`/`MESSAGE_UNPIN` audit log entry, if the message belongs to a guild
+/// channel. DM pins aren't guild-scoped, so there is nothing to attribute the entry to.
+async fn record_pin_audit_log(
+    db: &Database,
+    user_id: Snowflake,
+    channel_id: Snowflake,
+    message_id: Snowflake,
+    message: &Message,
+    pinned: bool,
+) {
+    let Some(guild_id) = message.guild_id else {
+        return;
+    };
+    let _ = AuditLogEntry::record_pin_change(db, guild_id, user_id, channel_id, message_id, pinned)
+        .await;
+}
+
+/// Publishes the `MESSAGE_UPDATE` and `CHANNEL_PINS_UPDATE` events every pin/unpin causes,
+/// regardless of which direction it went.
+///
+/// A guild channel's pin events are published on its guild's topic; DM pins, which have no guild
+/// to fall back on, go out on their own channel topic instead. `gateway_task` subscribes clients
+/// to both kinds of topic (guild membership and DM channel membership), so either way there's
+/// somebody listening.
+async fn emit_pin_events(dispatch: &DispatchRegistry, channel_id: Snowflake, message: &Message) {
+    let topic = match message.guild_id {
+        Some(guild_id) => Topic::Guild(guild_id),
+        None => Topic::Channel(channel_id),
+    };
+
+    if let Ok(event) = DispatchEvent::new("MESSAGE_UPDATE", &MessageUpdateEvent(message.clone())) {
+        dispatch.emit(topic, event).await;
+    }
+
+    if let Ok(event) = DispatchEvent::new(
+        "CHANNEL_PINS_UPDATE",
+        &ChannelPinsUpdateEvent {
+            guild_id: message.guild_id,
+            channel_id,
+            last_pin_timestamp: None,
+        },
+    ) {
+        dispatch.emit(topic, event).await;
+    }
+}
+
 #[handler]
 pub async fn get_pinned_messages(
-    Data(db): Data<&MySqlPool>,
+    Data(db): Data<&Database>,
     Path(channel_id): Path<Snowflake>,
 ) -> poem::Result<impl IntoResponse> {
     // TODO: Check permission 'READ_MESSAGE_HISTORY'
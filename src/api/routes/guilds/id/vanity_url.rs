@@ -9,21 +9,31 @@ use chorus::types::{
     GuildVanityInviteResponse, Snowflake,
 };
 use poem::{
-    handler,
+    get, handler,
     http::StatusCode,
     web::{Data, Json, Path},
-    IntoResponse,
+    EndpointExt, IntoResponse, Route,
 };
-use sqlx::MySqlPool;
 
 use crate::{
-    database::entities::{Guild, Invite},
+    api::middleware::{RateLimitMiddleware, RateLimitRoute},
+    database::{
+        backend::Database,
+        entities::{AuditLogEntry, Guild, Invite},
+    },
     errors::{Error, GuildError},
+    gateway::dispatch::{DispatchEvent, DispatchRegistry, Topic},
 };
 
+pub fn setup_routes() -> Route {
+    Route::new()
+        .at("/", get(get_vanity).patch(set_vanity))
+        .with(RateLimitMiddleware::new("guild_vanity_url", RateLimitRoute::Guild))
+}
+
 #[handler]
 pub async fn get_vanity(
-    Data(db): Data<&MySqlPool>,
+    Data(db): Data<&Database>,
     Data(claims): Data<&Claims>,
     Path(guild_id): Path<Snowflake>,
 ) -> poem::Result<impl IntoResponse> {
@@ -55,8 +65,9 @@ pub async fn get_vanity(
 
 #[handler]
 pub async fn set_vanity(
-    Data(db): Data<&MySqlPool>,
+    Data(db): Data<&Database>,
     Data(claims): Data<&Claims>,
+    Data(dispatch): Data<&DispatchRegistry>,
     Path(guild_id): Path<Snowflake>,
     Json(payload): Json<GuildCreateVanitySchema>,
 ) -> poem::Result<impl IntoResponse> {
@@ -70,12 +81,24 @@ pub async fn set_vanity(
 
     // TODO: Check permissions
 
+    let old_code = Invite::get_by_guild_vanity(db, guild.id)
+        .await?
+        .map(|invite| invite.code.to_owned())
+        .unwrap_or_default();
+
     if let Some(mut current_vanity) = Invite::get_by_guild_vanity(db, guild.id).await? {
         current_vanity.set_code(db, &payload.code).await?;
     } else {
         Invite::create_vanity(db, guild.id, &payload.code).await?;
     }
 
+    let _ = AuditLogEntry::record_vanity_update(db, guild_id, claims.id, &old_code, &payload.code)
+        .await;
+
+    if let Ok(event) = DispatchEvent::new("GUILD_UPDATE", &guild) {
+        dispatch.emit(Topic::Guild(guild_id), event).await;
+    }
+
     Ok(Json(GuildVanityInviteResponse {
         code: payload.code,
         uses: None,
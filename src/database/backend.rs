@@ -0,0 +1,73 @@
+use sqlx::{MySqlPool, PgPool};
+
+/// The database backend symfonia is currently configured to talk to.
+///
+/// Entity methods take a `&Database` instead of a concrete pool type so that the same code path
+/// works whether the instance was configured with MySQL or Postgres; which one is selected is a
+/// startup-time choice, not a per-call one.
+#[derive(Debug, Clone)]
+pub enum Database {
+    MySql(MySqlPool),
+    Postgres(PgPool),
+}
+
+impl Database {
+    /// Rewrites a query written with MySQL-style `?` placeholders into the dialect the backend
+    /// actually expects: left untouched for MySQL, renumbered into Postgres' `$1, $2, ...` for
+    /// Postgres.
+    ///
+    /// This only matters for queries built as plain strings (e.g. `get_by_id`); queries built
+    /// with [`sqlx::QueryBuilder`] should prefer `push_bind`, which already emits the correct
+    /// placeholder for whichever backend the builder is instantiated with.
+    pub fn rewrite_placeholders(&self, query: &str) -> std::borrow::Cow<'_, str> {
+        match self {
+            Database::MySql(_) => std::borrow::Cow::Borrowed(query),
+            Database::Postgres(_) => {
+                let mut rewritten = String::with_capacity(query.len());
+                let mut placeholder_count = 0usize;
+                for ch in query.chars() {
+                    if ch == '?' {
+                        placeholder_count += 1;
+                        rewritten.push('$');
+                        rewritten.push_str(&placeholder_count.to_string());
+                    } else {
+                        rewritten.push(ch);
+                    }
+                }
+                std::borrow::Cow::Owned(rewritten)
+            }
+        }
+    }
+
+    /// Returns the underlying MySQL pool, if this instance is configured with MySQL.
+    ///
+    /// Entities that haven't grown Postgres support of their own yet (most of them, currently)
+    /// extract the concrete pool at their boundary with this instead of matching on `Database`
+    /// themselves.
+    pub fn as_mysql(&self) -> Option<&MySqlPool> {
+        match self {
+            Database::MySql(pool) => Some(pool),
+            Database::Postgres(_) => None,
+        }
+    }
+
+    /// Returns the underlying Postgres pool, if this instance is configured with Postgres.
+    pub fn as_postgres(&self) -> Option<&PgPool> {
+        match self {
+            Database::MySql(_) => None,
+            Database::Postgres(pool) => Some(pool),
+        }
+    }
+}
+
+impl From<MySqlPool> for Database {
+    fn from(pool: MySqlPool) -> Self {
+        Database::MySql(pool)
+    }
+}
+
+impl From<PgPool> for Database {
+    fn from(pool: PgPool) -> Self {
+        Database::Postgres(pool)
+    }
+}
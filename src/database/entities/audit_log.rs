@@ -2,8 +2,9 @@ use std::ops::{Deref, DerefMut};
 
 use chorus::types::{AuditLogActionType, Snowflake};
 use serde::{Deserialize, Serialize};
-use sqlx::{FromRow, MySqlPool};
+use sqlx::{Database as SqlxDatabase, Encode, FromRow, QueryBuilder, Type};
 
+use crate::database::backend::Database;
 use crate::errors::Error;
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
@@ -28,20 +29,104 @@ impl DerefMut for AuditLogEntry {
 }
 
 impl AuditLogEntry {
-    pub async fn create(db: &MySqlPool) -> Result<Self, Error> {
-        todo!()
+    /// Persists a new audit log entry and returns it as stored.
+    ///
+    /// `changes` is serialized as-is into the `changes` column; most callers should prefer one of
+    /// the narrower `record_*` helpers below over calling this directly.
+    pub async fn create(
+        db: &Database,
+        guild_id: Snowflake,
+        user_id: Snowflake,
+        action_type: AuditLogActionType,
+        changes: serde_json::Value,
+    ) -> Result<Self, Error> {
+        let id = Snowflake::generate();
+        let query = db.rewrite_placeholders(
+            "INSERT INTO audit_logs (id, guild_id, user_id, action_type, changes) VALUES (?, ?, ?, ?, ?)",
+        );
+
+        match db {
+            Database::MySql(pool) => {
+                sqlx::query(&query)
+                    .bind(id)
+                    .bind(guild_id)
+                    .bind(user_id)
+                    .bind(action_type)
+                    .bind(&changes)
+                    .execute(pool)
+                    .await
+            }
+            Database::Postgres(pool) => {
+                sqlx::query(&query)
+                    .bind(id)
+                    .bind(guild_id)
+                    .bind(user_id)
+                    .bind(action_type)
+                    .bind(&changes)
+                    .execute(pool)
+                    .await
+            }
+        }
+        .map_err(Error::SQLX)?;
+
+        Self::get_by_id(db, id)
+            .await?
+            .ok_or(Error::SQLX(sqlx::Error::RowNotFound))
+    }
+
+    /// Records a `GUILD_UPDATE` entry for a vanity invite code change.
+    pub async fn record_vanity_update(
+        db: &Database,
+        guild_id: Snowflake,
+        user_id: Snowflake,
+        old_code: &str,
+        new_code: &str,
+    ) -> Result<Self, Error> {
+        Self::create(
+            db,
+            guild_id,
+            user_id,
+            AuditLogActionType::GuildUpdate,
+            serde_json::json!({ "vanity_url_code": { "old": old_code, "new": new_code } }),
+        )
+        .await
     }
 
-    pub async fn get_by_id(db: &MySqlPool, id: Snowflake) -> Result<Option<Self>, Error> {
-        sqlx::query_as("SELECT * FROM audit_logs WHERE id = ?")
-            .bind(id)
-            .fetch_optional(db)
-            .await
-            .map_err(Error::from)
+    /// Records a `MESSAGE_PIN`/`MESSAGE_UNPIN` entry for a pinned-message change.
+    pub async fn record_pin_change(
+        db: &Database,
+        guild_id: Snowflake,
+        user_id: Snowflake,
+        channel_id: Snowflake,
+        message_id: Snowflake,
+        pinned: bool,
+    ) -> Result<Self, Error> {
+        let action_type = if pinned {
+            AuditLogActionType::MessagePin
+        } else {
+            AuditLogActionType::MessageUnpin
+        };
+        Self::create(
+            db,
+            guild_id,
+            user_id,
+            action_type,
+            serde_json::json!({ "channel_id": channel_id, "message_id": message_id }),
+        )
+        .await
+    }
+
+    pub async fn get_by_id(db: &Database, id: Snowflake) -> Result<Option<Self>, Error> {
+        let query = db.rewrite_placeholders("SELECT * FROM audit_logs WHERE id = ?");
+        match db {
+            Database::MySql(pool) => sqlx::query_as(&query).bind(id).fetch_optional(pool).await,
+            Database::Postgres(pool) => sqlx::query_as(&query).bind(id).fetch_optional(pool).await,
+        }
+        .map_err(Error::from)
     }
 
     pub async fn get_by_guild(
-        db: &MySqlPool,
+        db: &Database,
         guild_id: Snowflake,
         before: Option<Snowflake>,
         after: Option<Snowflake>,
@@ -49,7 +134,68 @@ impl AuditLogEntry {
         user_id: Option<Snowflake>,
         action_type: Option<AuditLogActionType>,
     ) -> Result<Vec<Self>, Error> {
-        let mut builder = sqlx::QueryBuilder::new("SELECT * FROM audit_logs WHERE guild_id = ? ");
+        let rows = match db {
+            Database::MySql(pool) => {
+                let mut builder = Self::build_guild_query::<sqlx::MySql>(
+                    guild_id,
+                    before,
+                    after,
+                    limit,
+                    user_id,
+                    action_type,
+                );
+                builder
+                    .build()
+                    .fetch_all(pool)
+                    .await
+                    .map_err(Error::SQLX)?
+                    .into_iter()
+                    .filter_map(|row| AuditLogEntry::from_row(&row).ok())
+                    .collect::<Vec<_>>()
+            }
+            Database::Postgres(pool) => {
+                let mut builder = Self::build_guild_query::<sqlx::Postgres>(
+                    guild_id,
+                    before,
+                    after,
+                    limit,
+                    user_id,
+                    action_type,
+                );
+                builder
+                    .build()
+                    .fetch_all(pool)
+                    .await
+                    .map_err(Error::SQLX)?
+                    .into_iter()
+                    .filter_map(|row| AuditLogEntry::from_row(&row).ok())
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        Ok(rows)
+    }
+
+    /// Builds the filtered `get_by_guild` query, binding every parameter through `push_bind` so
+    /// the emitted placeholder already matches whichever backend `DB` is instantiated with
+    /// (MySQL's `?` or Postgres' `$N`), instead of hardcoding one dialect.
+    fn build_guild_query<'a, DB>(
+        guild_id: Snowflake,
+        before: Option<Snowflake>,
+        after: Option<Snowflake>,
+        limit: u8,
+        user_id: Option<Snowflake>,
+        action_type: Option<AuditLogActionType>,
+    ) -> QueryBuilder<'a, DB>
+    where
+        DB: SqlxDatabase,
+        Snowflake: Type<DB> + for<'q> Encode<'q, DB> + 'a,
+        u8: Type<DB> + for<'q> Encode<'q, DB> + 'a,
+        AuditLogActionType: Type<DB> + for<'q> Encode<'q, DB> + 'a,
+    {
+        let mut builder = QueryBuilder::new("SELECT * FROM audit_logs WHERE guild_id = ");
+        builder.push_bind(guild_id);
+        builder.push(" ");
 
         if let Some(before) = before {
             builder.push("AND id < ");
@@ -78,18 +224,7 @@ impl AuditLogEntry {
         builder.push("LIMIT ");
         builder.push_bind(limit);
 
-        let query = builder.build();
-
-        let r = query
-            .bind(guild_id)
-            .fetch_all(db)
-            .await
-            .map_err(Error::SQLX)?;
-
-        Ok(r.into_iter()
-            .map(|r| AuditLogEntry::from_row(&r))
-            .flatten()
-            .collect::<Vec<_>>())
+        builder
     }
 
     pub fn into_inner(self) -> chorus::types::AuditLogEntry {
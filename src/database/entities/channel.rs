@@ -0,0 +1,46 @@
+use std::ops::{Deref, DerefMut};
+
+use chorus::types::Snowflake;
+use serde::{Deserialize, Serialize};
+
+use crate::database::backend::Database;
+use crate::errors::Error;
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Channel {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    inner: chorus::types::Channel,
+}
+
+impl Deref for Channel {
+    type Target = chorus::types::Channel;
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl DerefMut for Channel {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+
+impl Channel {
+    /// Returns the ids of every DM/group DM channel `user_id` is a recipient of, for subscribing
+    /// a freshly connected gateway client to its channel topics. Guild channels don't need this:
+    /// their events go out on the guild's topic instead, see [`Topic`](crate::gateway::dispatch::Topic).
+    pub async fn dm_channel_ids_for_user(
+        db: &Database,
+        user_id: Snowflake,
+    ) -> Result<Vec<Snowflake>, Error> {
+        let query = db.rewrite_placeholders("SELECT channel_id FROM recipients WHERE user_id = ?");
+        let rows: Vec<(Snowflake,)> = match db {
+            Database::MySql(pool) => sqlx::query_as(&query).bind(user_id).fetch_all(pool).await,
+            Database::Postgres(pool) => sqlx::query_as(&query).bind(user_id).fetch_all(pool).await,
+        }
+        .map_err(Error::from)?;
+
+        Ok(rows.into_iter().map(|(channel_id,)| channel_id).collect())
+    }
+}
@@ -0,0 +1,71 @@
+use std::ops::{Deref, DerefMut};
+
+use chorus::types::Snowflake;
+use serde::{Deserialize, Serialize};
+
+use crate::database::backend::Database;
+use crate::errors::Error;
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Guild {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    inner: chorus::types::Guild,
+}
+
+impl Deref for Guild {
+    type Target = chorus::types::Guild;
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl DerefMut for Guild {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+
+impl Guild {
+    pub async fn get_by_id(db: &Database, id: Snowflake) -> Result<Option<Self>, Error> {
+        let query = db.rewrite_placeholders("SELECT * FROM guilds WHERE id = ?");
+        match db {
+            Database::MySql(pool) => sqlx::query_as(&query).bind(id).fetch_optional(pool).await,
+            Database::Postgres(pool) => sqlx::query_as(&query).bind(id).fetch_optional(pool).await,
+        }
+        .map_err(Error::from)
+    }
+
+    /// Returns the ids of every guild `user_id` is a member of, for subscribing a freshly
+    /// connected gateway client to its guild topics.
+    pub async fn ids_for_member(db: &Database, user_id: Snowflake) -> Result<Vec<Snowflake>, Error> {
+        let query = db.rewrite_placeholders("SELECT guild_id FROM guild_members WHERE user_id = ?");
+        let rows: Vec<(Snowflake,)> = match db {
+            Database::MySql(pool) => sqlx::query_as(&query).bind(user_id).fetch_all(pool).await,
+            Database::Postgres(pool) => sqlx::query_as(&query).bind(user_id).fetch_all(pool).await,
+        }
+        .map_err(Error::from)?;
+
+        Ok(rows.into_iter().map(|(guild_id,)| guild_id).collect())
+    }
+
+    pub async fn has_member(&self, db: &Database, user_id: Snowflake) -> Result<bool, Error> {
+        let query = db.rewrite_placeholders(
+            "SELECT COUNT(*) FROM guild_members WHERE guild_id = ? AND user_id = ?",
+        );
+        let count: (i64,) = match db {
+            Database::MySql(pool) => sqlx::query_as(&query)
+                .bind(self.id)
+                .bind(user_id)
+                .fetch_one(pool)
+                .await,
+            Database::Postgres(pool) => sqlx::query_as(&query)
+                .bind(self.id)
+                .bind(user_id)
+                .fetch_one(pool)
+                .await,
+        }
+        .map_err(Error::from)?;
+        Ok(count.0 > 0)
+    }
+}
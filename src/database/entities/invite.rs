@@ -0,0 +1,96 @@
+use std::ops::{Deref, DerefMut};
+
+use chorus::types::Snowflake;
+use serde::{Deserialize, Serialize};
+
+use crate::database::backend::Database;
+use crate::errors::Error;
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Invite {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    inner: chorus::types::Invite,
+}
+
+impl Deref for Invite {
+    type Target = chorus::types::Invite;
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl DerefMut for Invite {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+
+impl Invite {
+    pub async fn get_by_guild_vanity(
+        db: &Database,
+        guild_id: Snowflake,
+    ) -> Result<Option<Self>, Error> {
+        let query = db.rewrite_placeholders(
+            "SELECT * FROM invites WHERE guild_id = ? AND vanity = true",
+        );
+        match db {
+            Database::MySql(pool) => sqlx::query_as(&query)
+                .bind(guild_id)
+                .fetch_optional(pool)
+                .await,
+            Database::Postgres(pool) => sqlx::query_as(&query)
+                .bind(guild_id)
+                .fetch_optional(pool)
+                .await,
+        }
+        .map_err(Error::from)
+    }
+
+    pub async fn create_vanity(
+        db: &Database,
+        guild_id: Snowflake,
+        code: &str,
+    ) -> Result<Self, Error> {
+        let query = db.rewrite_placeholders(
+            "INSERT INTO invites (code, guild_id, vanity, uses) VALUES (?, ?, true, 0)",
+        );
+        match db {
+            Database::MySql(pool) => sqlx::query(&query)
+                .bind(code)
+                .bind(guild_id)
+                .execute(pool)
+                .await,
+            Database::Postgres(pool) => sqlx::query(&query)
+                .bind(code)
+                .bind(guild_id)
+                .execute(pool)
+                .await,
+        }
+        .map_err(Error::SQLX)?;
+
+        Self::get_by_guild_vanity(db, guild_id)
+            .await?
+            .ok_or(Error::SQLX(sqlx::Error::RowNotFound))
+    }
+
+    pub async fn set_code(&mut self, db: &Database, code: &str) -> Result<(), Error> {
+        let query = db.rewrite_placeholders("UPDATE invites SET code = ? WHERE code = ?");
+        match db {
+            Database::MySql(pool) => sqlx::query(&query)
+                .bind(code)
+                .bind(&self.code)
+                .execute(pool)
+                .await,
+            Database::Postgres(pool) => sqlx::query(&query)
+                .bind(code)
+                .bind(&self.code)
+                .execute(pool)
+                .await,
+        }
+        .map_err(Error::SQLX)?;
+
+        self.code = code.to_owned();
+        Ok(())
+    }
+}
@@ -0,0 +1,106 @@
+use std::ops::{Deref, DerefMut};
+
+use chorus::types::Snowflake;
+use serde::{Deserialize, Serialize};
+
+use crate::database::backend::Database;
+use crate::errors::Error;
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Message {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    inner: chorus::types::Message,
+}
+
+impl Deref for Message {
+    type Target = chorus::types::Message;
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl DerefMut for Message {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+
+impl Message {
+    pub async fn get_by_id(
+        db: &Database,
+        channel_id: Snowflake,
+        message_id: Snowflake,
+    ) -> Result<Option<Self>, Error> {
+        let query =
+            db.rewrite_placeholders("SELECT * FROM messages WHERE channel_id = ? AND id = ?");
+        match db {
+            Database::MySql(pool) => sqlx::query_as(&query)
+                .bind(channel_id)
+                .bind(message_id)
+                .fetch_optional(pool)
+                .await,
+            Database::Postgres(pool) => sqlx::query_as(&query)
+                .bind(channel_id)
+                .bind(message_id)
+                .fetch_optional(pool)
+                .await,
+        }
+        .map_err(Error::from)
+    }
+
+    pub async fn get_pinned(db: &Database, channel_id: Snowflake) -> Result<Vec<Self>, Error> {
+        let query = db.rewrite_placeholders(
+            "SELECT * FROM messages WHERE channel_id = ? AND pinned = true",
+        );
+        match db {
+            Database::MySql(pool) => sqlx::query_as(&query)
+                .bind(channel_id)
+                .fetch_all(pool)
+                .await,
+            Database::Postgres(pool) => sqlx::query_as(&query)
+                .bind(channel_id)
+                .fetch_all(pool)
+                .await,
+        }
+        .map_err(Error::from)
+    }
+
+    pub async fn count_pinned(db: &Database, channel_id: Snowflake) -> Result<i32, Error> {
+        let query = db.rewrite_placeholders(
+            "SELECT COUNT(*) FROM messages WHERE channel_id = ? AND pinned = true",
+        );
+        let count: (i64,) = match db {
+            Database::MySql(pool) => sqlx::query_as(&query)
+                .bind(channel_id)
+                .fetch_one(pool)
+                .await,
+            Database::Postgres(pool) => sqlx::query_as(&query)
+                .bind(channel_id)
+                .fetch_one(pool)
+                .await,
+        }
+        .map_err(Error::from)?;
+        Ok(count.0 as i32)
+    }
+
+    pub async fn set_pinned(&mut self, db: &Database, pinned: bool) -> Result<(), Error> {
+        let query = db.rewrite_placeholders("UPDATE messages SET pinned = ? WHERE id = ?");
+        match db {
+            Database::MySql(pool) => sqlx::query(&query)
+                .bind(pinned)
+                .bind(self.id)
+                .execute(pool)
+                .await,
+            Database::Postgres(pool) => sqlx::query(&query)
+                .bind(pinned)
+                .bind(self.id)
+                .execute(pool)
+                .await,
+        }
+        .map_err(Error::SQLX)?;
+
+        self.pinned = pinned;
+        Ok(())
+    }
+}
@@ -0,0 +1,38 @@
+use std::ops::{Deref, DerefMut};
+
+use chorus::types::Snowflake;
+use serde::{Deserialize, Serialize};
+
+use crate::database::backend::Database;
+use crate::errors::Error;
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct User {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    inner: chorus::types::User,
+}
+
+impl Deref for User {
+    type Target = chorus::types::User;
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl DerefMut for User {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+
+impl User {
+    pub async fn get_by_id(db: &Database, id: Snowflake) -> Result<Option<Self>, Error> {
+        let query = db.rewrite_placeholders("SELECT * FROM users WHERE id = ?");
+        match db {
+            Database::MySql(pool) => sqlx::query_as(&query).bind(id).fetch_optional(pool).await,
+            Database::Postgres(pool) => sqlx::query_as(&query).bind(id).fetch_optional(pool).await,
+        }
+        .map_err(Error::from)
+    }
+}
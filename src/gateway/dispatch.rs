@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use chorus::types::Snowflake;
+use serde::Serialize;
+use tokio::sync::{broadcast, Mutex};
+
+/// The number of not-yet-forwarded events a slow subscriber may fall behind by before it starts
+/// missing them.
+const TOPIC_CHANNEL_CAPACITY: usize = 64;
+
+/// Identifies a set of gateway subscribers interested in the same slice of state: either
+/// everything happening in a guild, or everything happening in a single channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Topic {
+    Guild(Snowflake),
+    Channel(Snowflake),
+}
+
+/// A dispatch event, ready to be forwarded to subscribers as a gateway dispatch frame once paired
+/// with a sequence number.
+#[derive(Debug, Clone)]
+pub struct DispatchEvent {
+    pub name: &'static str,
+    pub data: serde_json::Value,
+}
+
+impl DispatchEvent {
+    pub fn new<T: Serialize>(name: &'static str, data: &T) -> serde_json::Result<Self> {
+        Ok(Self {
+            name,
+            data: serde_json::to_value(data)?,
+        })
+    }
+}
+
+/// A [`DispatchEvent`] paired with a sequence number, in the standard gateway envelope shape
+/// (`op`/`t`/`s`/`d`) ready to be written to the wire.
+#[derive(Debug, Clone, Serialize)]
+pub struct GatewayDispatchFrame {
+    pub op: u8,
+    pub t: &'static str,
+    pub s: u64,
+    pub d: serde_json::Value,
+}
+
+/// Gateway opcode for a dispatch frame, per the Discord/Spacebar gateway protocol.
+pub const DISPATCH_OPCODE: u8 = 0;
+
+/// Central pub/sub hub bridging REST mutations to gateway subscribers.
+///
+/// Poem handlers call [`DispatchRegistry::emit`] after a successful database write; `gateway_task`
+/// subscribes a connected `GatewayClient` to every guild topic its user has membership in (via
+/// [`GatewayUser::subscriptions`](crate::gateway::GatewayUser::subscriptions)) plus a channel
+/// topic for each of its DM/group DM channels, and forwards anything broadcast on them to the
+/// socket as dispatch frames with incrementing sequence numbers.
+#[derive(Clone, Default)]
+pub struct DispatchRegistry {
+    topics: Arc<Mutex<HashMap<Topic, broadcast::Sender<DispatchEvent>>>>,
+}
+
+impl DispatchRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Subscribes to a topic, creating its broadcast channel if this is the first subscriber.
+    pub async fn subscribe(&self, topic: Topic) -> broadcast::Receiver<DispatchEvent> {
+        let mut topics = self.topics.lock().await;
+        topics
+            .entry(topic)
+            .or_insert_with(|| broadcast::channel(TOPIC_CHANNEL_CAPACITY).0)
+            .subscribe()
+    }
+
+    /// Publishes `event` to every current subscriber of `topic`.
+    ///
+    /// A topic with no subscribers is a no-op rather than an error: a REST mutation must not fail
+    /// just because nobody happens to be connected to the gateway right now.
+    pub async fn emit(&self, topic: Topic, event: DispatchEvent) {
+        let topics = self.topics.lock().await;
+        if let Some(sender) = topics.get(&topic) {
+            // Errors here only mean there are currently no live receivers, which is fine.
+            let _ = sender.send(event);
+        }
+    }
+}
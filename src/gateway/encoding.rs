@@ -0,0 +1,323 @@
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress, Status};
+
+use crate::errors::{Error, GatewayError};
+
+/// The 4-byte marker a `zlib-stream` sender appends to every sync-flushed deflate block.
+///
+/// A frame is only complete once the rolling inbound buffer ends with this sequence; until
+/// then, more binary frames need to be appended to it.
+pub const ZLIB_SYNC_FLUSH_SUFFIX: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+
+/// Payload encoding negotiated via the `encoding` query parameter on the gateway connection URI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GatewayEncoding {
+    #[default]
+    Json,
+    Etf,
+}
+
+impl GatewayEncoding {
+    fn from_query_value(value: &str) -> Self {
+        match value {
+            "etf" => Self::Etf,
+            _ => Self::Json,
+        }
+    }
+}
+
+/// Payload compression negotiated via the `compress` query parameter on the gateway connection URI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GatewayCompression {
+    #[default]
+    None,
+    ZlibStream,
+    ZstdStream,
+}
+
+impl GatewayCompression {
+    fn from_query_value(value: &str) -> Self {
+        match value {
+            "zlib-stream" => Self::ZlibStream,
+            "zstd-stream" => Self::ZstdStream,
+            _ => Self::None,
+        }
+    }
+}
+
+/// Parses the `encoding` and `compress` query parameters off of the gateway connection URI, e.g.
+/// `/?encoding=etf&compress=zlib-stream`. Unknown or missing values fall back to `json`/no
+/// compression, matching how real clients have always been allowed to omit them.
+pub fn negotiate_from_query(query: &str) -> (GatewayEncoding, GatewayCompression) {
+    let mut encoding = GatewayEncoding::default();
+    let mut compression = GatewayCompression::default();
+
+    for pair in query.split('&') {
+        let mut parts = pair.splitn(2, '=');
+        let (key, value) = match (parts.next(), parts.next()) {
+            (Some(key), Some(value)) => (key, value),
+            _ => continue,
+        };
+
+        match key {
+            "encoding" => encoding = GatewayEncoding::from_query_value(value),
+            "compress" => compression = GatewayCompression::from_query_value(value),
+            _ => {}
+        }
+    }
+
+    (encoding, compression)
+}
+
+/// Per-connection streaming compression state.
+///
+/// `zlib-stream` keeps one deflate/inflate pair alive for the lifetime of the connection, so its
+/// shared dictionary carries across every message. `zstd-stream` reuses its compressor and
+/// decompressor too, but compresses each message into an independent zstd frame: no dictionary
+/// is shared between messages.
+pub enum CompressionContext {
+    None,
+    ZlibStream {
+        inflate: Box<Decompress>,
+        deflate: Box<Compress>,
+        /// Bytes received so far for the message currently being assembled. Cleared once it
+        /// ends in [`ZLIB_SYNC_FLUSH_SUFFIX`] and has been inflated.
+        inbound_buffer: Vec<u8>,
+    },
+    ZstdStream {
+        encoder: Box<zstd::bulk::Compressor<'static>>,
+        decoder: Box<zstd::bulk::Decompressor<'static>>,
+        inbound_buffer: Vec<u8>,
+    },
+}
+
+impl CompressionContext {
+    pub fn new(compression: GatewayCompression) -> Result<Self, Error> {
+        Ok(match compression {
+            GatewayCompression::None => Self::None,
+            GatewayCompression::ZlibStream => Self::ZlibStream {
+                inflate: Box::new(Decompress::new(true)),
+                deflate: Box::new(Compress::new(Compression::default(), true)),
+                inbound_buffer: Vec::new(),
+            },
+            GatewayCompression::ZstdStream => Self::ZstdStream {
+                encoder: Box::new(
+                    zstd::bulk::Compressor::new().map_err(|_| GatewayError::Compression)?,
+                ),
+                decoder: Box::new(
+                    zstd::bulk::Decompressor::new().map_err(|_| GatewayError::Compression)?,
+                ),
+                inbound_buffer: Vec::new(),
+            },
+        })
+    }
+
+    /// Feeds a newly received binary frame into the rolling inbound buffer and, once a full
+    /// message has arrived, returns the inflated payload.
+    ///
+    /// For `zlib-stream` this means waiting for the buffer to end in [`ZLIB_SYNC_FLUSH_SUFFIX`];
+    /// `zstd-stream` frames are already message-delimited by the websocket layer, so every frame
+    /// decompresses on its own.
+    pub fn decode_frame(&mut self, frame: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+        match self {
+            Self::None => Ok(Some(frame.to_vec())),
+            Self::ZlibStream {
+                inflate,
+                inbound_buffer,
+                ..
+            } => {
+                inbound_buffer.extend_from_slice(frame);
+                if !inbound_buffer.ends_with(&ZLIB_SYNC_FLUSH_SUFFIX) {
+                    return Ok(None);
+                }
+
+                // `decompress_vec` only ever writes into `out`'s existing spare capacity, and a
+                // sync flush doesn't produce `Status::StreamEnd` (the stream stays open across
+                // messages), so "one call returned" is no stopping condition. The message is done
+                // once all of its input has been consumed *and* the last call left room in `out`:
+                // inflate can take in the last input bytes while still holding output back for
+                // lack of space. Grow `out` and call again until both hold.
+                let mut out = Vec::with_capacity(inbound_buffer.len() * 4);
+                let start_total_in = inflate.total_in();
+                loop {
+                    let consumed = (inflate.total_in() - start_total_in) as usize;
+                    let status = inflate
+                        .decompress_vec(&inbound_buffer[consumed..], &mut out, FlushDecompress::Sync)
+                        .map_err(|_| GatewayError::Compression)?;
+
+                    let consumed = (inflate.total_in() - start_total_in) as usize;
+                    if consumed >= inbound_buffer.len() && out.len() < out.capacity() {
+                        break;
+                    }
+
+                    if status == Status::BufError || out.len() == out.capacity() {
+                        let grow_by = out.capacity().max(inbound_buffer.len());
+                        out.reserve(grow_by);
+                    }
+                }
+                inbound_buffer.clear();
+                Ok(Some(out))
+            }
+            Self::ZstdStream { decoder, .. } => decoder
+                .decompress(frame, frame.len() * 10)
+                .map(Some)
+                .map_err(|_| GatewayError::Compression.into()),
+        }
+    }
+
+    /// Compresses an outbound payload. `zlib-stream` finishes the deflate block with a sync flush
+    /// so the shared dictionary carries over to the next message; `zstd-stream` emits one
+    /// complete, independent zstd frame per payload.
+    pub fn encode_frame(&mut self, payload: &[u8]) -> Result<Vec<u8>, Error> {
+        match self {
+            Self::None => Ok(payload.to_vec()),
+            Self::ZlibStream { deflate, .. } => {
+                // Same reasoning as `decode_frame`'s inflate loop: `compress_vec` only ever
+                // writes into `out`'s existing spare capacity, and deflate output can exceed the
+                // input length (block headers, the sync-flush suffix), so a single call sized at
+                // `payload.len()` can silently truncate the frame. Deflate can also consume all
+                // of `payload` while the flushed block is still pending for lack of space, so
+                // grow `out` and call again until all of `payload` has been consumed and the last
+                // call left room in `out`.
+                let mut out = Vec::with_capacity(payload.len());
+                let start_total_in = deflate.total_in();
+                loop {
+                    let consumed = (deflate.total_in() - start_total_in) as usize;
+                    let status = deflate
+                        .compress_vec(&payload[consumed..], &mut out, FlushCompress::Sync)
+                        .map_err(|_| GatewayError::Compression)?;
+
+                    let consumed = (deflate.total_in() - start_total_in) as usize;
+                    if consumed >= payload.len() && out.len() < out.capacity() {
+                        break;
+                    }
+
+                    if status == Status::BufError || out.len() == out.capacity() {
+                        let grow_by = out.capacity().max(payload.len());
+                        out.reserve(grow_by);
+                    }
+                }
+                Ok(out)
+            }
+            Self::ZstdStream { encoder, .. } => encoder
+                .compress(payload)
+                .map_err(|_| GatewayError::Compression.into()),
+        }
+    }
+}
+
+/// Encodes a gateway payload for the wire according to the negotiated [`GatewayEncoding`],
+/// returning the encoded bytes (UTF-8 JSON or ETF); the caller picks the websocket frame type.
+pub fn encode_payload<T: serde::Serialize>(
+    encoding: GatewayEncoding,
+    payload: &T,
+) -> Result<Vec<u8>, Error> {
+    match encoding {
+        GatewayEncoding::Json => {
+            serde_json::to_vec(payload).map_err(|_| GatewayError::Serialization.into())
+        }
+        GatewayEncoding::Etf => {
+            let term = serde_eetf::to_term(payload).map_err(|_| GatewayError::Serialization)?;
+            let mut buf = Vec::new();
+            term.encode(&mut buf)
+                .map_err(|_| GatewayError::Serialization)?;
+            Ok(buf)
+        }
+    }
+}
+
+/// Decodes a gateway payload received on the wire according to the negotiated [`GatewayEncoding`].
+pub fn decode_payload<T: serde::de::DeserializeOwned>(
+    encoding: GatewayEncoding,
+    bytes: &[u8],
+) -> Result<T, Error> {
+    match encoding {
+        GatewayEncoding::Json => {
+            serde_json::from_slice(bytes).map_err(|_| GatewayError::Deserialization.into())
+        }
+        GatewayEncoding::Etf => {
+            let term = eetf::Term::decode(bytes).map_err(|_| GatewayError::Deserialization)?;
+            serde_eetf::from_term(term).map_err(|_| GatewayError::Deserialization.into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negotiate_from_query_reads_known_values() {
+        let (encoding, compression) = negotiate_from_query("encoding=etf&compress=zlib-stream");
+        assert_eq!(encoding, GatewayEncoding::Etf);
+        assert_eq!(compression, GatewayCompression::ZlibStream);
+    }
+
+    #[test]
+    fn negotiate_from_query_falls_back_on_unknown_or_missing_values() {
+        let (encoding, compression) = negotiate_from_query("encoding=msgpack&foo=bar");
+        assert_eq!(encoding, GatewayEncoding::Json);
+        assert_eq!(compression, GatewayCompression::None);
+
+        let (encoding, compression) = negotiate_from_query("");
+        assert_eq!(encoding, GatewayEncoding::Json);
+        assert_eq!(compression, GatewayCompression::None);
+    }
+
+    #[test]
+    fn zlib_stream_round_trips_a_single_message() {
+        let mut sender = CompressionContext::new(GatewayCompression::ZlibStream).unwrap();
+        let mut receiver = CompressionContext::new(GatewayCompression::ZlibStream).unwrap();
+
+        let payload = br#"{"op":0,"t":"READY","s":1,"d":{}}"#;
+        let frame = sender.encode_frame(payload).unwrap();
+        let decoded = receiver.decode_frame(&frame).unwrap().unwrap();
+
+        assert_eq!(decoded, payload);
+    }
+
+    #[test]
+    fn zlib_stream_round_trips_a_message_larger_than_the_initial_output_guess() {
+        // `decode_frame` sizes its first output buffer at 4x the compressed frame's length; a
+        // highly compressible, large payload blows well past that and exercises the `BufError`
+        // growth loop instead of returning after a single `decompress_vec` call.
+        let mut sender = CompressionContext::new(GatewayCompression::ZlibStream).unwrap();
+        let mut receiver = CompressionContext::new(GatewayCompression::ZlibStream).unwrap();
+
+        let payload = "a".repeat(1_000_000).into_bytes();
+        let frame = sender.encode_frame(&payload).unwrap();
+        let decoded = receiver.decode_frame(&frame).unwrap().unwrap();
+
+        assert_eq!(decoded, payload);
+    }
+
+    #[test]
+    fn zlib_stream_round_trips_a_payload_that_does_not_compress() {
+        // Random bytes deflate to roughly their own size plus block/sync-flush overhead, so the
+        // `Vec::with_capacity(payload.len())` guess in `encode_frame` undershoots immediately and
+        // exercises its growth loop, not just `decode_frame`'s.
+        let mut sender = CompressionContext::new(GatewayCompression::ZlibStream).unwrap();
+        let mut receiver = CompressionContext::new(GatewayCompression::ZlibStream).unwrap();
+
+        let payload: Vec<u8> = (0..100_000).map(|_| rand::random::<u8>()).collect();
+        let frame = sender.encode_frame(&payload).unwrap();
+        let decoded = receiver.decode_frame(&frame).unwrap().unwrap();
+
+        assert_eq!(decoded, payload);
+    }
+
+    #[test]
+    fn zlib_stream_buffers_until_the_sync_flush_suffix_arrives() {
+        let mut sender = CompressionContext::new(GatewayCompression::ZlibStream).unwrap();
+        let mut receiver = CompressionContext::new(GatewayCompression::ZlibStream).unwrap();
+
+        let payload = br#"{"op":0,"t":"READY","s":1,"d":{}}"#;
+        let frame = sender.encode_frame(payload).unwrap();
+
+        // Split the frame mid-stream; nothing should decode until the suffix-bearing half lands.
+        let (first, second) = frame.split_at(frame.len() / 2);
+        assert!(receiver.decode_frame(first).unwrap().is_none());
+        let decoded = receiver.decode_frame(second).unwrap().unwrap();
+
+        assert_eq!(decoded, payload);
+    }
+}
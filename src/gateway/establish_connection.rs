@@ -7,21 +7,28 @@ use chorus::types::{
 use futures::{SinkExt, StreamExt};
 use log::trace;
 use rand::seq;
-use serde_json::{from_str, json};
-use sqlx::PgPool;
 use tokio::net::TcpStream;
 use tokio::sync::broadcast::Sender;
 use tokio::sync::Mutex;
 use tokio::task::JoinHandle;
-use tokio_tungstenite::accept_async;
+use tokio_tungstenite::accept_hdr_async;
+use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
 use tokio_tungstenite::tungstenite::Message;
 
-use crate::database::entities::Config;
+use crate::database::backend::Database;
+use crate::database::entities::{Channel, Config, Guild};
 use crate::errors::{Error, GatewayError};
+use crate::gateway::dispatch::DispatchRegistry;
+use crate::gateway::encoding::{
+    decode_payload, encode_payload, negotiate_from_query, CompressionContext, GatewayCompression,
+    GatewayEncoding,
+};
 use crate::gateway::heartbeat::HeartbeatHandler;
 use crate::gateway::resume_connection::resume_connection;
+use crate::gateway::session_store::SessionStore;
 use crate::gateway::{gateway_task, GatewayUser};
 use crate::util::token::check_token;
+use rand::Rng;
 
 use super::{Connection, GatewayClient, GatewayUsersStore, NewConnection};
 
@@ -32,19 +39,42 @@ use super::{Connection, GatewayClient, GatewayUsersStore, NewConnection};
 /// [GatewayClient], whose `.parent` field contains a [Weak] reference to the new [GatewayUser].
 pub(super) async fn establish_connection(
     stream: TcpStream,
-    db: PgPool, // TODO: Do we need db here?
+    db: Database,
     config: Config,
     gateway_users_store: GatewayUsersStore,
+    session_store: SessionStore,
+    dispatch: DispatchRegistry,
 ) -> Result<NewConnection, Error> {
     trace!(target: "symfonia::gateway::establish_connection", "Beginning process to establish connection (handshake)");
-    let ws_stream = accept_async(stream).await?;
+
+    // Clients negotiate `encoding=json|etf` and `compress=zlib-stream|zstd-stream` through the
+    // connection URI's query string, e.g. `/?encoding=etf&compress=zlib-stream`. We need the
+    // request before the websocket upgrade completes, so we use `accept_hdr_async` instead of
+    // `accept_async` to peek at it.
+    let mut negotiated = (GatewayEncoding::default(), GatewayCompression::default());
+    let callback = |request: &Request, response: Response| {
+        negotiated = request
+            .uri()
+            .query()
+            .map(negotiate_from_query)
+            .unwrap_or_default();
+        Ok(response)
+    };
+    let ws_stream = accept_hdr_async(stream, callback).await?;
+    let (encoding, compression) = negotiated;
+    trace!(target: "symfonia::gateway::establish_connection", "Negotiated encoding {:?}, compression {:?}", encoding, compression);
+
+    let compression = Arc::new(Mutex::new(CompressionContext::new(compression)?));
     let mut connection: Connection = ws_stream.split().into();
     trace!(target: "symfonia::gateway::establish_connection", "Sending hello message");
     // Hello message
-    connection
-        .sender
-        .send(Message::Text(json!(GatewayHello::default()).to_string()))
-        .await?;
+    send_encoded(
+        &mut connection,
+        encoding,
+        &compression,
+        &GatewayHello::default(),
+    )
+    .await?;
     trace!(target: "symfonia::gateway::establish_connection", "Sent hello message");
 
     let connection = Arc::new(Mutex::new(connection));
@@ -80,10 +110,15 @@ pub(super) async fn establish_connection(
             message_receive,
             message_send,
             sequence_number,
+            session_id_send,
             session_id_receive,
             db,
             &config,
             gateway_users_store.clone(),
+            encoding,
+            compression,
+            session_store,
+            dispatch,
         ) => {
             return new_connection;
         }
@@ -92,9 +127,34 @@ pub(super) async fn establish_connection(
     todo!()
 }
 
+/// Encodes and compresses `payload` according to the connection's negotiated
+/// [`GatewayEncoding`]/[`GatewayCompression`] and writes it to the socket.
+///
+/// ETF payloads and any compressed payload are sent as binary frames; plain JSON is sent as text,
+/// matching what existing clients expect when no encoding/compression was negotiated.
+pub(super) async fn send_encoded<T: serde::Serialize>(
+    connection: &mut Connection,
+    encoding: GatewayEncoding,
+    compression: &Arc<Mutex<CompressionContext>>,
+    payload: &T,
+) -> Result<(), Error> {
+    let encoded = encode_payload(encoding, payload)?;
+    let mut compression = compression.lock().await;
+    let message = if matches!(*compression, CompressionContext::None) {
+        match encoding {
+            GatewayEncoding::Json => Message::Text(String::from_utf8_lossy(&encoded).into_owned()),
+            GatewayEncoding::Etf => Message::Binary(encoded),
+        }
+    } else {
+        Message::Binary(compression.encode_frame(&encoded)?)
+    };
+    connection.sender.send(message).await?;
+    Ok(())
+}
+
 /// `get_or_new_gateway_user` is a helper function that retrieves a [GatewayUser] from the store if it exists,
 /// or creates a new user, stores it in the store and then returns it, if it does not exist.
-async fn get_or_new_gateway_user(
+pub(super) async fn get_or_new_gateway_user(
     user_id: Snowflake,
     store: GatewayUsersStore,
 ) -> Arc<tokio::sync::Mutex<GatewayUser>> {
@@ -111,6 +171,44 @@ async fn get_or_new_gateway_user(
     user
 }
 
+/// Populates `user`'s `subscriptions` with every guild `user_id` currently belongs to, so
+/// `gateway_task` has somewhere to forward dispatch events to. Merges into whatever's already
+/// there instead of overwriting it, since `user` may be shared across several concurrent
+/// connections for the same account.
+///
+/// Must be called, and awaited, before `gateway_task` is spawned for this connection:
+/// `gateway_task` reads `subscriptions` once at startup, so populating it afterwards would race.
+pub(super) async fn subscribe_to_member_guilds(
+    user: &Arc<Mutex<GatewayUser>>,
+    db: &Database,
+    user_id: Snowflake,
+) {
+    let guild_ids = Guild::ids_for_member(db, user_id).await.unwrap_or_else(|_| {
+        log::warn!(target: "symfonia::gateway::establish_connection", "Failed to load guild memberships for {user_id}, client will receive no guild events");
+        Vec::new()
+    });
+
+    let mut user = user.lock().await;
+    for guild_id in guild_ids {
+        if !user.subscriptions.contains(&guild_id) {
+            user.subscriptions.push(guild_id);
+        }
+    }
+}
+
+/// Returns the ids of every DM/group DM channel `user_id` is a recipient of, so `gateway_task`
+/// can subscribe to their `Topic::Channel` topics. DM pin events (and anything else with no guild
+/// to fall back on) are published on these topics instead of a guild's, so without this a DM
+/// channel's events have no subscriber and are silently dropped.
+pub(super) async fn dm_channel_ids_for_user(db: &Database, user_id: Snowflake) -> Vec<Snowflake> {
+    Channel::dm_channel_ids_for_user(db, user_id)
+        .await
+        .unwrap_or_else(|_| {
+            log::warn!(target: "symfonia::gateway::establish_connection", "Failed to load DM channel memberships for {user_id}, client will receive no DM channel events");
+            Vec::new()
+        })
+}
+
 async fn finish_connecting(
     connection: Arc<Mutex<Connection>>,
     mut heartbeat_handler_handle: Option<JoinHandle<()>>,
@@ -119,10 +217,15 @@ async fn finish_connecting(
     message_receive: tokio::sync::broadcast::Receiver<GatewayHeartbeat>,
     message_send: tokio::sync::broadcast::Sender<GatewayHeartbeat>,
     sequence_number: Arc<Mutex<u64>>,
+    session_id_send: tokio::sync::broadcast::Sender<String>,
     session_id_receive: tokio::sync::broadcast::Receiver<String>,
-    db: PgPool,
+    db: Database,
     config: &Config,
     gateway_users_store: GatewayUsersStore,
+    encoding: GatewayEncoding,
+    compression: Arc<Mutex<CompressionContext>>,
+    session_store: SessionStore,
+    dispatch: DispatchRegistry,
 ) -> Result<NewConnection, Error> {
     loop {
         trace!(target: "symfonia::gateway::establish_connection", "No resume or identify message received yet, waiting for next message...");
@@ -133,7 +236,22 @@ async fn finish_connecting(
         }?;
         trace!(target: "symfonia::gateway::establish_connection", "Received message: {:?}", raw_message);
 
-        if let Ok(heartbeat) = from_str::<GatewayHeartbeat>(&raw_message.to_string()) {
+        let frame_bytes = match &raw_message {
+            Message::Text(text) => text.clone().into_bytes(),
+            Message::Binary(bytes) => bytes.clone(),
+            _ => {
+                trace!(target: "symfonia::gateway::establish_connection", "Received unexpected message: {:?}", raw_message);
+                return Err(GatewayError::UnexpectedMessage.into());
+            }
+        };
+        let decoded_bytes = match compression.lock().await.decode_frame(&frame_bytes)? {
+            Some(bytes) => bytes,
+            // A `zlib-stream` message may be split across multiple binary frames; keep reading
+            // until the rolling buffer ends in the sync-flush marker.
+            None => continue,
+        };
+
+        if let Ok(heartbeat) = decode_payload::<GatewayHeartbeat>(encoding, &decoded_bytes) {
             log::trace!(target: "symfonia::gateway::establish_connection", "Received heartbeat");
             match heartbeat_handler_handle {
                 None => {
@@ -154,6 +272,8 @@ async fn finish_connecting(
                             message_receive.resubscribe(),
                             sequence_number.clone(),
                             session_id_receive.resubscribe(),
+                            encoding,
+                            compression.clone(),
                         );
                         async move {
                             heartbeat_handler.run().await;
@@ -164,7 +284,7 @@ async fn finish_connecting(
                     message_send.send(heartbeat);
                 }
             }
-        } else if let Ok(identify) = from_str::<GatewayIdentifyPayload>(&raw_message.to_string()) {
+        } else if let Ok(identify) = decode_payload::<GatewayIdentifyPayload>(encoding, &decoded_bytes) {
             log::trace!(target: "symfonia::gateway::establish_connection", "Received identify payload");
             let claims = match check_token(&db, &identify.token, &config.security.jwt_secret).await
             {
@@ -177,10 +297,31 @@ async fn finish_connecting(
             };
             let mut gateway_user =
                 get_or_new_gateway_user(claims.id, gateway_users_store.clone()).await;
+            subscribe_to_member_guilds(&gateway_user, &db, claims.id).await;
+
+            let session_id: String = rand::thread_rng()
+                .sample_iter(&rand::distributions::Alphanumeric)
+                .take(32)
+                .map(char::from)
+                .collect();
+            session_store.create_session(session_id.clone(), claims.id).await;
+            let _ = session_id_send.send(session_id.clone());
+            let dm_channel_ids = dm_channel_ids_for_user(&db, claims.id).await;
+
             let gateway_client = GatewayClient {
                 parent: Arc::downgrade(&gateway_user),
                 connection: connection.clone(),
-                main_task_handle: tokio::spawn(gateway_task::gateway_task(connection.clone())),
+                main_task_handle: tokio::spawn(gateway_task::gateway_task(
+                    connection.clone(),
+                    gateway_user.clone(),
+                    dispatch.clone(),
+                    session_store.clone(),
+                    session_id.clone(),
+                    sequence_number.clone(),
+                    encoding,
+                    compression.clone(),
+                    dm_channel_ids,
+                )),
                 heartbeat_task_handle: match heartbeat_handler_handle {
                     Some(handle) => handle,
                     None => tokio::spawn({
@@ -191,6 +332,8 @@ async fn finish_connecting(
                             message_receive.resubscribe(),
                             sequence_number.clone(),
                             session_id_receive.resubscribe(),
+                            encoding,
+                            compression.clone(),
                         );
                         async move {
                             heartbeat_handler.run().await;
@@ -211,9 +354,20 @@ async fn finish_connecting(
                 user: gateway_user,
                 client: gateway_client_arc_mutex.clone(),
             });
-        } else if let Ok(resume) = from_str::<GatewayResume>(&raw_message.to_string()) {
+        } else if let Ok(resume) = decode_payload::<GatewayResume>(encoding, &decoded_bytes) {
             log::trace!(target: "symfonia::gateway::establish_connection", "Received resume payload");
-            return resume_connection(connection, db, config.to_owned(), resume).await;
+            return resume_connection(
+                connection,
+                db,
+                config.to_owned(),
+                resume,
+                session_store.clone(),
+                dispatch,
+                gateway_users_store,
+                encoding,
+                compression,
+            )
+            .await;
         } else {
             trace!(target: "symfonia::gateway::establish_connection", "Received unexpected message: {:?}", raw_message);
             return Err(GatewayError::UnexpectedMessage.into());
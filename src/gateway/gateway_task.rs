@@ -0,0 +1,113 @@
+use std::sync::Arc;
+
+use chorus::types::Snowflake;
+use log::trace;
+use tokio::sync::{mpsc, Mutex};
+
+use crate::gateway::dispatch::{DispatchEvent, DispatchRegistry, GatewayDispatchFrame, Topic};
+use crate::gateway::encoding::{CompressionContext, GatewayEncoding};
+use crate::gateway::establish_connection::send_encoded;
+use crate::gateway::session_store::SessionStore;
+use crate::gateway::{Connection, GatewayUser};
+
+/// The main per-client gateway task, spawned once a client has identified or resumed.
+///
+/// Subscribes to every guild topic `user` currently has membership in, plus a `Topic::Channel`
+/// for each of `dm_channel_ids` (the user's DM/group DM channels, which have no guild to fall
+/// back on), then forwards whatever the dispatch registry broadcasts on those topics to the
+/// socket as gateway dispatch frames (`op: 0`) with an incrementing sequence number, recording
+/// each one into the session's replay buffer as it goes so a later RESUME can replay it. Marks
+/// the session disconnected once forwarding stops, so `SessionStore::evict_expired` can reclaim
+/// it after the grace period.
+pub async fn gateway_task(
+    connection: Arc<Mutex<Connection>>,
+    user: Arc<Mutex<GatewayUser>>,
+    dispatch: DispatchRegistry,
+    session_store: SessionStore,
+    session_id: String,
+    sequence_number: Arc<Mutex<u64>>,
+    encoding: GatewayEncoding,
+    compression: Arc<Mutex<CompressionContext>>,
+    dm_channel_ids: Vec<Snowflake>,
+) {
+    let guild_ids = user.lock().await.subscriptions.clone();
+    let topics = build_topics(&guild_ids, dm_channel_ids);
+
+    if topics.is_empty() {
+        trace!(target: "symfonia::gateway::gateway_task", "Client has no subscriptions, nothing to forward");
+        session_store.mark_disconnected(&session_id).await;
+        return;
+    }
+
+    // Each topic gets its own broadcast receiver; a plain task per receiver funnels everything
+    // into one mpsc channel so the forwarding loop below only has to watch a single stream.
+    let (forward_send, mut forward_receive) = mpsc::channel::<DispatchEvent>(64);
+    for topic in topics {
+        let mut receiver = dispatch.subscribe(topic).await;
+        let forward_send = forward_send.clone();
+        tokio::spawn(async move {
+            while let Ok(event) = receiver.recv().await {
+                if forward_send.send(event).await.is_err() {
+                    break;
+                }
+            }
+        });
+    }
+    drop(forward_send);
+
+    while let Some(event) = forward_receive.recv().await {
+        let sequence_number = {
+            let mut sequence_number = sequence_number.lock().await;
+            *sequence_number += 1;
+            *sequence_number
+        };
+
+        session_store
+            .record(&session_id, sequence_number, event.clone())
+            .await;
+
+        let frame = GatewayDispatchFrame {
+            op: 0,
+            t: event.name,
+            s: sequence_number,
+            d: event.data,
+        };
+
+        let mut connection = connection.lock().await;
+        if let Err(error) = send_encoded(&mut connection, encoding, &compression, &frame).await {
+            trace!(target: "symfonia::gateway::gateway_task", "Failed to forward dispatch event, stopping: {:?}", error);
+            break;
+        }
+    }
+
+    session_store.mark_disconnected(&session_id).await;
+}
+
+/// Builds the set of topics a client should subscribe to: one `Topic::Guild` per guild
+/// membership, plus one `Topic::Channel` per DM/group DM channel.
+fn build_topics(guild_ids: &[Snowflake], dm_channel_ids: Vec<Snowflake>) -> Vec<Topic> {
+    let mut topics: Vec<Topic> = guild_ids.iter().map(|guild_id| Topic::Guild(*guild_id)).collect();
+    topics.extend(dm_channel_ids.into_iter().map(Topic::Channel));
+    topics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_topics_includes_both_guild_and_dm_channel_topics() {
+        let guild_ids = vec![Snowflake::from(1u64)];
+        let dm_channel_ids = vec![Snowflake::from(2u64)];
+
+        let topics = build_topics(&guild_ids, dm_channel_ids);
+
+        assert!(topics.contains(&Topic::Guild(Snowflake::from(1u64))));
+        assert!(topics.contains(&Topic::Channel(Snowflake::from(2u64))));
+    }
+
+    #[test]
+    fn build_topics_is_empty_with_no_subscriptions() {
+        assert!(build_topics(&[], Vec::new()).is_empty());
+    }
+}
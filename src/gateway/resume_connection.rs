@@ -0,0 +1,131 @@
+use std::sync::Arc;
+
+use chorus::types::{GatewayHeartbeat, GatewayInvalidSession, GatewayResume};
+use log::trace;
+use tokio::sync::Mutex;
+
+use crate::database::backend::Database;
+use crate::database::entities::Config;
+use crate::errors::{Error, GatewayError, UserError};
+use crate::gateway::dispatch::{DispatchRegistry, GatewayDispatchFrame};
+use crate::gateway::encoding::{CompressionContext, GatewayEncoding};
+use crate::gateway::establish_connection::{
+    dm_channel_ids_for_user, get_or_new_gateway_user, send_encoded, subscribe_to_member_guilds,
+};
+use crate::gateway::gateway_task;
+use crate::gateway::heartbeat::HeartbeatHandler;
+use crate::gateway::session_store::SessionStore;
+use crate::gateway::{Connection, GatewayClient, GatewayUsersStore, NewConnection};
+use crate::util::token::check_token;
+
+/// Validates a RESUME attempt against the session's replay buffer and either replays whatever the
+/// client missed and hands dispatch back to a fresh `gateway_task`/heartbeat pair, or, if the
+/// session is unknown or its `seq` has already been evicted from the buffer, tells the client its
+/// session is invalid so it re-identifies instead.
+pub(super) async fn resume_connection(
+    connection: Arc<Mutex<Connection>>,
+    db: Database,
+    config: Config,
+    resume: GatewayResume,
+    session_store: SessionStore,
+    dispatch: DispatchRegistry,
+    gateway_users_store: GatewayUsersStore,
+    encoding: GatewayEncoding,
+    compression: Arc<Mutex<CompressionContext>>,
+) -> Result<NewConnection, Error> {
+    let claims = match check_token(&db, &resume.token, &config.security.jwt_secret).await {
+        Ok(claims) => claims,
+        Err(_) => {
+            trace!(target: "symfonia::gateway::resume_connection", "Failed to verify token on resume");
+            return Err(UserError::InvalidToken.into());
+        }
+    };
+
+    let Some(replay) = session_store
+        .resume(&resume.session_id, claims.id, resume.seq)
+        .await
+    else {
+        trace!(target: "symfonia::gateway::resume_connection", "Session {} could not be resumed, telling client to re-identify", resume.session_id);
+        let mut conn = connection.lock().await;
+        // `false` means "not resumable": the client should IDENTIFY fresh rather than retry RESUME.
+        let _ = send_encoded(&mut conn, encoding, &compression, &GatewayInvalidSession(false)).await;
+        return Err(GatewayError::Closed.into());
+    };
+
+    // If the replay buffer is empty the client was already fully caught up (`resume.seq` is the
+    // latest sequence it's seen), so the post-resume counter must carry on from there rather than
+    // from 0 — otherwise the next live dispatch would go out tagged with a sequence number lower
+    // than one the client already acked.
+    let last_replayed_seq = replay
+        .last()
+        .map(|entry| entry.sequence_number)
+        .unwrap_or(resume.seq);
+    let sequence_number = Arc::new(Mutex::new(last_replayed_seq));
+
+    {
+        let mut conn = connection.lock().await;
+        for entry in &replay {
+            let frame = GatewayDispatchFrame {
+                op: 0,
+                t: entry.event.name,
+                s: entry.sequence_number,
+                d: entry.event.data.clone(),
+            };
+            send_encoded(&mut conn, encoding, &compression, &frame).await?;
+        }
+    }
+
+    let gateway_user = get_or_new_gateway_user(claims.id, gateway_users_store).await;
+    subscribe_to_member_guilds(&gateway_user, &db, claims.id).await;
+    let dm_channel_ids = dm_channel_ids_for_user(&db, claims.id).await;
+
+    let (kill_send, kill_receive) = tokio::sync::broadcast::channel::<()>(1);
+    let (_message_send, message_receive) = tokio::sync::broadcast::channel::<GatewayHeartbeat>(4);
+    let (_session_id_send, session_id_receive) = tokio::sync::broadcast::channel::<String>(1);
+
+    let gateway_client = GatewayClient {
+        parent: Arc::downgrade(&gateway_user),
+        connection: connection.clone(),
+        main_task_handle: tokio::spawn(gateway_task::gateway_task(
+            connection.clone(),
+            gateway_user.clone(),
+            dispatch,
+            session_store,
+            resume.session_id.clone(),
+            sequence_number.clone(),
+            encoding,
+            compression.clone(),
+            dm_channel_ids,
+        )),
+        heartbeat_task_handle: tokio::spawn({
+            let mut heartbeat_handler = HeartbeatHandler::new(
+                connection.clone(),
+                kill_receive,
+                kill_send.clone(),
+                message_receive,
+                sequence_number,
+                session_id_receive,
+                encoding,
+                compression.clone(),
+            );
+            async move {
+                heartbeat_handler.run().await;
+            }
+        }),
+        kill_send,
+        disconnect_info: None,
+        session_token: resume.token,
+    };
+
+    let gateway_client_arc_mutex = Arc::new(Mutex::new(gateway_client));
+    gateway_user
+        .lock()
+        .await
+        .clients
+        .push(gateway_client_arc_mutex.clone());
+
+    Ok(NewConnection {
+        user: gateway_user,
+        client: gateway_client_arc_mutex,
+    })
+}
@@ -0,0 +1,180 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chorus::types::Snowflake;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+use crate::gateway::dispatch::DispatchEvent;
+
+/// How many past events a session buffer retains for replay on RESUME.
+const REPLAY_BUFFER_CAPACITY: usize = 250;
+
+/// How long a session buffer is kept around after its socket drops, so a briefly disconnected
+/// client can still RESUME without losing events.
+const SESSION_GRACE_PERIOD: Duration = Duration::from_secs(60);
+
+/// A single buffered event, tagged with the sequence number it was dispatched at.
+#[derive(Debug, Clone)]
+pub struct ReplayEntry {
+    pub sequence_number: u64,
+    pub event: DispatchEvent,
+}
+
+/// Bounded ring buffer of the events dispatched to one session, keyed by session id in
+/// [`SessionStore`].
+struct SessionBuffer {
+    user_id: Snowflake,
+    entries: VecDeque<ReplayEntry>,
+    /// Set when the socket drops; the buffer is evicted once this is older than
+    /// [`SESSION_GRACE_PERIOD`].
+    disconnected_at: Option<Instant>,
+}
+
+impl SessionBuffer {
+    fn new(user_id: Snowflake) -> Self {
+        Self {
+            user_id,
+            entries: VecDeque::with_capacity(REPLAY_BUFFER_CAPACITY),
+            disconnected_at: None,
+        }
+    }
+
+    fn record(&mut self, sequence_number: u64, event: DispatchEvent) {
+        if self.entries.len() == REPLAY_BUFFER_CAPACITY {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(ReplayEntry {
+            sequence_number,
+            event,
+        });
+    }
+
+    /// Returns every buffered event with a sequence number greater than `since`, in dispatch
+    /// order, or `None` if the event right after `since` was already evicted from the buffer and
+    /// the client needs to re-identify instead of resuming.
+    fn replay_since(&self, since: u64) -> Option<Vec<ReplayEntry>> {
+        if let Some(oldest) = self.entries.front() {
+            if since + 1 < oldest.sequence_number {
+                return None;
+            }
+        }
+        Some(
+            self.entries
+                .iter()
+                .filter(|entry| entry.sequence_number > since)
+                .cloned()
+                .collect(),
+        )
+    }
+}
+
+/// Keeps one bounded replay buffer per live (or recently disconnected) session id.
+///
+/// `establish_connection` allocates a session and its buffer on IDENTIFY; `resume_connection`
+/// validates the session id and `seq` a RESUME attempt provides against it before replaying
+/// missed events and handing live dispatch back to the client.
+#[derive(Clone, Default)]
+pub struct SessionStore {
+    sessions: Arc<Mutex<HashMap<String, SessionBuffer>>>,
+}
+
+impl SessionStore {
+    pub fn new() -> Self {
+        let store = Self::default();
+        store.spawn_janitor();
+        store
+    }
+
+    /// Spawns a background task that periodically sweeps expired session buffers, so a
+    /// long-running process doesn't accumulate disconnected sessions forever.
+    fn spawn_janitor(&self) {
+        let store = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(SESSION_GRACE_PERIOD);
+            loop {
+                interval.tick().await;
+                store.evict_expired().await;
+            }
+        });
+    }
+
+    /// Allocates a new, empty session buffer on IDENTIFY.
+    pub async fn create_session(&self, session_id: String, user_id: Snowflake) {
+        let mut sessions = self.sessions.lock().await;
+        sessions.insert(session_id, SessionBuffer::new(user_id));
+    }
+
+    /// Records a dispatched event against a session's replay buffer.
+    pub async fn record(&self, session_id: &str, sequence_number: u64, event: DispatchEvent) {
+        let mut sessions = self.sessions.lock().await;
+        if let Some(buffer) = sessions.get_mut(session_id) {
+            buffer.record(sequence_number, event);
+        }
+    }
+
+    /// Validates a RESUME attempt and returns the events to replay, or `None` if the session is
+    /// unknown/expired, belongs to a different user than `user_id` (`session_id` is an unsigned
+    /// bare string, not proof of ownership — that's what this check is for), or `since` has
+    /// already been evicted from the buffer. In all three cases the caller should send an
+    /// invalid-session signal and have the client re-identify instead.
+    pub async fn resume(
+        &self,
+        session_id: &str,
+        user_id: Snowflake,
+        since: u64,
+    ) -> Option<Vec<ReplayEntry>> {
+        let mut sessions = self.sessions.lock().await;
+        let buffer = sessions.get_mut(session_id)?;
+        if buffer.user_id != user_id {
+            return None;
+        }
+        buffer.disconnected_at = None;
+        buffer.replay_since(since)
+    }
+
+    /// Marks a session as disconnected. It survives for [`SESSION_GRACE_PERIOD`] so a briefly
+    /// disconnected client can reconnect and resume without losing events, and is then cleaned up
+    /// by [`Self::evict_expired`].
+    pub async fn mark_disconnected(&self, session_id: &str) {
+        let mut sessions = self.sessions.lock().await;
+        if let Some(buffer) = sessions.get_mut(session_id) {
+            buffer.disconnected_at = Some(Instant::now());
+        }
+    }
+
+    /// Drops every session buffer that has been disconnected for longer than the grace period.
+    /// Meant to be driven by a periodic background task.
+    pub async fn evict_expired(&self) {
+        let mut sessions = self.sessions.lock().await;
+        sessions.retain(|_, buffer| {
+            buffer
+                .disconnected_at
+                .map(|at| at.elapsed() < SESSION_GRACE_PERIOD)
+                .unwrap_or(true)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn resume_rejects_a_user_id_that_does_not_own_the_session() {
+        let store = SessionStore::default();
+        let owner: Snowflake = 1.into();
+        let attacker: Snowflake = 2.into();
+        store.create_session("session-a".to_string(), owner).await;
+
+        assert!(store.resume("session-a", attacker, 0).await.is_none());
+        assert!(store.resume("session-a", owner, 0).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn resume_rejects_an_unknown_session() {
+        let store = SessionStore::default();
+        assert!(store.resume("does-not-exist", 1.into(), 0).await.is_none());
+    }
+}
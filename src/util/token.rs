@@ -0,0 +1,21 @@
+use chorus::types::jwt::Claims;
+
+use crate::database::backend::Database;
+use crate::database::entities::User;
+use crate::errors::{Error, UserError};
+
+/// Verifies that `token` is a validly-signed JWT for a user that still exists, returning its
+/// claims.
+///
+/// This re-checks the user's existence against `db` on every call (rather than trusting the JWT
+/// signature alone) so a deleted account's outstanding tokens stop working immediately instead of
+/// lingering until they expire.
+pub async fn check_token(db: &Database, token: &str, jwt_secret: &str) -> Result<Claims, Error> {
+    let claims = Claims::decode(token, jwt_secret.as_bytes()).map_err(|_| UserError::InvalidToken)?;
+
+    User::get_by_id(db, claims.id)
+        .await?
+        .ok_or(UserError::InvalidToken)?;
+
+    Ok(claims)
+}